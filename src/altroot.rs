@@ -0,0 +1,176 @@
+//! An adapter that reroots another VFS under a sub-path, for a chroot-like view of convenience
+//!
+//! This is **not** a security boundary: it only rewrites path strings before delegating to the
+//! inner VFS, so it is only meant to make it convenient to treat a fixture directory or a
+//! sub-directory of a `PhysicalFS` as if it were the root of its own file system.
+
+use std::fmt;
+
+use crate::{OpenOptions, Result, VMetadata, VfsError, VfsFile, VPath, VFS};
+
+/// Adapter that reroots an inner VFS under the given `root` path
+pub struct AltrootFS {
+    root: VPath,
+}
+
+impl AltrootFS {
+    /// Creates a new `AltrootFS`, rerooting the file system backing `root` at `root`'s path
+    pub fn new(root: VPath) -> Self {
+        AltrootFS { root }
+    }
+
+    /// Normalizes `path` (stripping empty/`.` segments, rejecting `..` escapes) and joins it onto
+    /// the altroot, returning the resulting path in the inner VFS
+    fn rewrite(&self, path: &str) -> Result<String> {
+        let mut result = self.root.path().to_string();
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => continue,
+                ".." => {
+                    return Err(VfsError::Other {
+                        message: format!("Path '{}' escapes the altroot", path),
+                    })
+                }
+                segment => {
+                    result.push('/');
+                    result.push_str(segment);
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl fmt::Debug for AltrootFS {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AltrootFS(\"{}\")", self.root.path())
+    }
+}
+
+impl VFS for AltrootFS {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = String>>> {
+        self.root.fs.vfs.read_dir(&self.rewrite(path)?)
+    }
+
+    fn create_dir(&self, path: &str) -> Result<()> {
+        self.root.fs.vfs.create_dir(&self.rewrite(path)?)
+    }
+
+    fn open_with_options(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn VfsFile>> {
+        self.root.fs.vfs.open_with_options(&self.rewrite(path)?, options)
+    }
+
+    fn metadata(&self, path: &str) -> Result<VMetadata> {
+        self.root.fs.vfs.metadata(&self.rewrite(path)?)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        match self.rewrite(path) {
+            Ok(rewritten) => self.root.fs.vfs.exists(&rewritten),
+            Err(_) => false,
+        }
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        self.root.fs.vfs.remove_file(&self.rewrite(path)?)
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        self.root.fs.vfs.remove_dir(&self.rewrite(path)?)
+    }
+
+    fn rename(&self, src: &str, dest: &str) -> Result<()> {
+        self.root.fs.vfs.rename(&self.rewrite(src)?, &self.rewrite(dest)?)
+    }
+
+    fn copy_file(&self, src: &str, dest: &str) -> Result<()> {
+        self.root.fs.vfs.copy_file(&self.rewrite(src)?, &self.rewrite(dest)?)
+    }
+
+    fn create_symlink(&self, target: &str, link: &str) -> Result<()> {
+        self.root.fs.vfs.create_symlink(target, &self.rewrite(link)?)
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        self.root.fs.vfs.read_link(&self.rewrite(path)?)
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<VMetadata> {
+        self.root.fs.vfs.symlink_metadata(&self.rewrite(path)?)
+    }
+
+    #[cfg(feature = "mmap")]
+    fn open_mmap(&self, path: &str) -> Result<Box<dyn AsRef<[u8]>>> {
+        self.root.fs.vfs.open_mmap(&self.rewrite(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryFS;
+    use std::io::{Read, Write};
+
+    fn altroot() -> AltrootFS {
+        let root = VPath::create(MemoryFS::new()).unwrap().join("sandbox");
+        root.create_dir_all().unwrap();
+        AltrootFS::new(root)
+    }
+
+    #[test]
+    fn rewrite_joins_path_onto_the_root() {
+        let fs = altroot();
+        assert_eq!(fs.rewrite("/foo/bar").unwrap(), "/sandbox/foo/bar");
+    }
+
+    #[test]
+    fn rewrite_skips_empty_and_dot_segments() {
+        let fs = altroot();
+        assert_eq!(fs.rewrite("//foo/./bar").unwrap(), "/sandbox/foo/bar");
+    }
+
+    #[test]
+    fn rewrite_rejects_dot_dot_escapes() {
+        let fs = altroot();
+        assert!(fs.rewrite("/foo/../bar").is_err());
+        assert!(fs.rewrite("..").is_err());
+    }
+
+    #[test]
+    fn operations_are_confined_to_the_altroot() {
+        let fs = altroot();
+        fs.create_dir("/foo").unwrap();
+        assert!(fs.exists("/foo"));
+        assert!(!fs.root.fs.vfs.exists("/foo"));
+        assert!(fs.root.fs.vfs.exists("/sandbox/foo"));
+    }
+
+    #[test]
+    fn exists_returns_false_for_escaping_paths_instead_of_erroring() {
+        let fs = altroot();
+        assert!(!fs.exists("/../outside"));
+    }
+
+    #[test]
+    fn create_symlink_stores_the_target_verbatim_not_rewritten() {
+        let fs = altroot();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.create_symlink("a.txt", "/link").unwrap();
+        // `target` must pass through untouched: it's not rewritten onto the altroot, so reading
+        // it back gives the original string, not the rewritten `/sandbox/a.txt` backing path.
+        assert_eq!(fs.read_link("/link").unwrap(), "a.txt");
+    }
+
+    #[test]
+    fn create_symlink_with_a_target_resolvable_in_the_backing_vfs_still_opens() {
+        let fs = altroot();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        // The backing MemoryFS resolves symlink targets as raw keys into its own (rewritten)
+        // namespace, so a target usable for `open_file` through this altroot must name the
+        // backing path directly.
+        fs.create_symlink("/sandbox/a.txt", "/link").unwrap();
+        let mut contents = String::new();
+        fs.open_file("/link").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+    }
+}
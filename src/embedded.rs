@@ -0,0 +1,324 @@
+//! A read-only file system that packs a whole directory tree into a single in-memory blob
+//!
+//! Every file's bytes are concatenated into one contiguous buffer at build time, and each path is
+//! recorded as an `(offset, len)` pair into that buffer alongside a directory tree of entries.
+//! This mirrors the single-blob + offset-table packing technique used by Deno's standalone VFS
+//! builder, and is a convenient way to bundle assets or test fixtures into a binary (e.g. via
+//! `include_bytes!`) while still serving them through the regular `VPath` API.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+
+use crate::{OpenOptions, Result, VFileType, VMetadata, VfsError, VfsFile, VPath, VFS};
+
+#[derive(Debug, Clone)]
+enum EmbeddedEntry {
+    File { offset: usize, len: usize },
+    Directory(HashMap<String, EmbeddedEntry>),
+}
+
+/// Builds an [`EmbeddedFS`] by packing files into a single buffer
+#[derive(Debug, Default)]
+pub struct EmbeddedFsBuilder {
+    buffer: Vec<u8>,
+    root: HashMap<String, EmbeddedEntry>,
+}
+
+impl EmbeddedFsBuilder {
+    /// Creates a new, empty builder
+    pub fn new() -> Self {
+        EmbeddedFsBuilder::default()
+    }
+
+    /// Packs a single file at `path` (segments separated by `/`) with the given contents. Fails
+    /// if a segment of `path` descends into a path that was already packed as a file.
+    pub fn add_file(&mut self, path: &str, contents: &[u8]) -> Result<&mut Self> {
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(contents);
+        let entry = EmbeddedEntry::File {
+            offset,
+            len: contents.len(),
+        };
+        insert_at(&mut self.root, path, entry)?;
+        Ok(self)
+    }
+
+    /// Walks every file reachable from `source` (recursively) and packs it, preserving its
+    /// relative path. `source` may point at a `PhysicalFS` directory, a `MemoryFS` fixture, or
+    /// any other `VFS` implementation.
+    pub fn add_dir(&mut self, source: &VPath) -> Result<&mut Self> {
+        self.add_dir_at(source, "")?;
+        Ok(self)
+    }
+
+    fn add_dir_at(&mut self, source: &VPath, prefix: &str) -> Result<()> {
+        for child in source.read_dir()? {
+            let name = child
+                .path()
+                .rsplit('/')
+                .next()
+                .unwrap_or(child.path())
+                .to_string();
+            let child_path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            match child.metadata()?.file_type {
+                VFileType::Directory => self.add_dir_at(&child, &child_path)?,
+                // `metadata()` always follows symlinks, so in practice this packs the link's
+                // target contents under the link's path.
+                VFileType::File | VFileType::Symlink => {
+                    let mut contents = Vec::new();
+                    child.open_file()?.read_to_end(&mut contents)?;
+                    self.add_file(&child_path, &contents)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Consumes the builder, producing the packed, immutable `EmbeddedFS`
+    pub fn build(self) -> EmbeddedFS {
+        EmbeddedFS {
+            buffer: Arc::new(self.buffer),
+            root: EmbeddedEntry::Directory(self.root),
+        }
+    }
+}
+
+fn insert_at(root: &mut HashMap<String, EmbeddedEntry>, path: &str, entry: EmbeddedEntry) -> Result<()> {
+    let mut current = root;
+    let mut segments = path.trim_matches('/').split('/').peekable();
+    while let Some(segment) = segments.next() {
+        if segments.peek().is_none() {
+            current.insert(segment.to_string(), entry);
+            return Ok(());
+        }
+        let next = current
+            .entry(segment.to_string())
+            .or_insert_with(|| EmbeddedEntry::Directory(HashMap::new()));
+        current = match next {
+            EmbeddedEntry::Directory(children) => children,
+            EmbeddedEntry::File { .. } => {
+                return Err(VfsError::Other {
+                    message: format!("path segment '{}' collides with an already packed file", segment),
+                })
+            }
+        };
+    }
+    Ok(())
+}
+
+/// A read-only file system serving files out of a single packed buffer
+#[derive(Debug)]
+pub struct EmbeddedFS {
+    buffer: Arc<Vec<u8>>,
+    root: EmbeddedEntry,
+}
+
+impl EmbeddedFS {
+    fn lookup(&self, path: &str) -> Option<&EmbeddedEntry> {
+        let mut current = &self.root;
+        if path.trim_matches('/').is_empty() {
+            return Some(current);
+        }
+        for segment in path.trim_matches('/').split('/') {
+            match current {
+                EmbeddedEntry::Directory(children) => current = children.get(segment)?,
+                EmbeddedEntry::File { .. } => return None,
+            }
+        }
+        Some(current)
+    }
+}
+
+/// A read-only cursor over the `[offset, offset + len)` slice of an [`EmbeddedFS`]'s shared
+/// buffer, read directly against the `Arc` without cloning the bytes
+#[derive(Debug)]
+struct EmbeddedFile {
+    buffer: Arc<Vec<u8>>,
+    offset: usize,
+    len: usize,
+    position: usize,
+}
+
+impl Read for EmbeddedFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = &self.buffer[self.offset + self.position..self.offset + self.len];
+        let n = remaining.len().min(buf.len());
+        buf[..n].copy_from_slice(&remaining[..n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+impl Write for EmbeddedFile {
+    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "EmbeddedFS is read-only"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for EmbeddedFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new_position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => self.len as i64 + offset,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if new_position < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek before start of file",
+            ));
+        }
+        self.position = new_position as usize;
+        Ok(self.position as u64)
+    }
+}
+
+fn read_only_error() -> VfsError {
+    VfsError::Other {
+        message: "EmbeddedFS is read-only".to_string(),
+    }
+}
+
+impl VFS for EmbeddedFS {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = String>>> {
+        match self.lookup(path) {
+            Some(EmbeddedEntry::Directory(children)) => Ok(Box::new(
+                children.keys().cloned().collect::<Vec<_>>().into_iter(),
+            )),
+            Some(EmbeddedEntry::File { .. }) => Err(VfsError::Other {
+                message: format!("'{}' is a file", path),
+            }),
+            None => Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    fn create_dir(&self, _path: &str) -> Result<()> {
+        Err(read_only_error())
+    }
+
+    fn open_with_options(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn VfsFile>> {
+        if options.write || options.create || options.create_new || options.append || options.truncate {
+            return Err(read_only_error());
+        }
+        match self.lookup(path) {
+            Some(EmbeddedEntry::File { offset, len }) => Ok(Box::new(EmbeddedFile {
+                buffer: self.buffer.clone(),
+                offset: *offset,
+                len: *len,
+                position: 0,
+            })),
+            Some(EmbeddedEntry::Directory(_)) => Err(VfsError::Other {
+                message: format!("'{}' is a directory", path),
+            }),
+            None => Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    fn metadata(&self, path: &str) -> Result<VMetadata> {
+        match self.lookup(path) {
+            Some(EmbeddedEntry::File { len, .. }) => Ok(VMetadata {
+                file_type: VFileType::File,
+                len: *len as u64,
+            }),
+            Some(EmbeddedEntry::Directory(_)) => Ok(VMetadata {
+                file_type: VFileType::Directory,
+                len: 0,
+            }),
+            None => Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.lookup(path).is_some()
+    }
+
+    fn remove_file(&self, _path: &str) -> Result<()> {
+        Err(read_only_error())
+    }
+
+    fn remove_dir(&self, _path: &str) -> Result<()> {
+        Err(read_only_error())
+    }
+
+    fn rename(&self, _src: &str, _dest: &str) -> Result<()> {
+        Err(read_only_error())
+    }
+
+    fn create_symlink(&self, _target: &str, _link: &str) -> Result<()> {
+        Err(read_only_error())
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        match self.lookup(path) {
+            Some(_) => Err(VfsError::Other {
+                message: format!("'{}' is not a symlink", path),
+            }),
+            None => Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<VMetadata> {
+        self.metadata(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_packed_file_contents() {
+        let mut builder = EmbeddedFsBuilder::new();
+        builder.add_file("dir/a.txt", b"hello").unwrap();
+        let fs = builder.build();
+        let mut contents = String::new();
+        fs.open_file("/dir/a.txt").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+    }
+
+    #[test]
+    fn seeking_does_not_copy_the_shared_buffer() {
+        let mut builder = EmbeddedFsBuilder::new();
+        builder.add_file("a.txt", b"hello world").unwrap();
+        let fs = builder.build();
+        let mut file = fs.open_file("/a.txt").unwrap();
+        file.seek(SeekFrom::Start(6)).unwrap();
+        assert_eq!(Arc::strong_count(&fs.buffer), 2);
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "world");
+    }
+
+    #[test]
+    fn add_file_fails_when_path_collides_with_a_packed_file() {
+        let mut builder = EmbeddedFsBuilder::new();
+        builder.add_file("a", b"contents").unwrap();
+        assert!(builder.add_file("a/b", b"contents").is_err());
+    }
+
+    #[test]
+    fn write_to_embedded_file_is_unsupported() {
+        let mut builder = EmbeddedFsBuilder::new();
+        builder.add_file("a.txt", b"hello").unwrap();
+        let fs = builder.build();
+        let mut file = fs.open_with_options("/a.txt", OpenOptions::new().read(true)).unwrap();
+        assert!(file.write(b"x").is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! Path interning with anchored relative-path resolution
+//!
+//! Tools built on top of the VFS (language servers, build graphs) want a cheap integer handle for
+//! a path instead of repeatedly hashing and comparing strings, and a correct way to resolve a
+//! relative include/import against the file that referenced it. [`FileSet`] provides both,
+//! following the anchored-path model used by rust-analyzer's vfs.
+
+use std::collections::HashMap;
+
+use crate::VPath;
+
+/// A stable handle for a path registered in a [`FileSet`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FileId(u32);
+
+/// A path expressed relative to the file already known as `anchor`, e.g. the target of an
+/// `#include` or `import` statement appearing in `anchor`
+#[derive(Copy, Clone, Debug)]
+pub struct AnchoredPath<'a> {
+    pub anchor: FileId,
+    pub path: &'a str,
+}
+
+impl<'a> AnchoredPath<'a> {
+    pub fn new(anchor: FileId, path: &'a str) -> Self {
+        AnchoredPath { anchor, path }
+    }
+}
+
+/// A bidirectional map from paths to stable [`FileId`]s, plus anchored-path resolution
+#[derive(Debug, Default)]
+pub struct FileSet {
+    path_to_id: HashMap<String, FileId>,
+    id_to_path: Vec<String>,
+}
+
+impl FileSet {
+    pub fn new() -> Self {
+        FileSet::default()
+    }
+
+    /// Registers `path` in the set, returning its (possibly newly assigned) stable id
+    pub fn intern(&mut self, path: &VPath) -> FileId {
+        if let Some(&id) = self.path_to_id.get(path.path()) {
+            return id;
+        }
+        let id = FileId(self.id_to_path.len() as u32);
+        self.id_to_path.push(path.path().to_string());
+        self.path_to_id.insert(path.path().to_string(), id);
+        id
+    }
+
+    /// Returns the path a previously interned id refers to
+    pub fn path(&self, file: FileId) -> &str {
+        &self.id_to_path[file.0 as usize]
+    }
+
+    /// Returns the id of `path`, if it has been registered
+    pub fn id(&self, path: &str) -> Option<FileId> {
+        self.path_to_id.get(path).copied()
+    }
+
+    /// Resolves `anchored.path` relative to the containing directory of `anchored.anchor`,
+    /// applying the relative path segment-by-segment (`.` is skipped, `..` pops a segment), and
+    /// returns the id of the result if it is registered in this set. Returns `None` both when the
+    /// resolved path isn't in the set and when `..` would escape above the set's root.
+    pub fn resolve_path(&self, anchored: AnchoredPath) -> Option<FileId> {
+        let anchor_path = self.path(anchored.anchor);
+        let mut segments: Vec<&str> = match anchor_path.rfind('/') {
+            Some(end) => anchor_path[..end].split('/').filter(|s| !s.is_empty()).collect(),
+            None => Vec::new(),
+        };
+        for segment in anchored.path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    segments.pop()?;
+                }
+                segment => segments.push(segment),
+            }
+        }
+        let resolved = format!("/{}", segments.join("/"));
+        self.id(&resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryFS;
+
+    fn vpath(path: &str) -> VPath {
+        VPath::create(MemoryFS::new()).unwrap().join(path.trim_start_matches('/'))
+    }
+
+    #[test]
+    fn resolve_path_finds_a_sibling_relative_to_the_anchor() {
+        let mut set = FileSet::new();
+        let anchor = set.intern(&vpath("/a/b.txt"));
+        let sibling = set.intern(&vpath("/a/c.txt"));
+        let resolved = set.resolve_path(AnchoredPath::new(anchor, "c.txt"));
+        assert_eq!(resolved, Some(sibling));
+    }
+
+    #[test]
+    fn resolve_path_applies_dot_dot_to_escape_a_subdirectory() {
+        let mut set = FileSet::new();
+        let anchor = set.intern(&vpath("/a/sub/b.txt"));
+        let target = set.intern(&vpath("/a/c.txt"));
+        let resolved = set.resolve_path(AnchoredPath::new(anchor, "../c.txt"));
+        assert_eq!(resolved, Some(target));
+    }
+
+    #[test]
+    fn resolve_path_skips_dot_segments() {
+        let mut set = FileSet::new();
+        let anchor = set.intern(&vpath("/a/b.txt"));
+        let sibling = set.intern(&vpath("/a/c.txt"));
+        let resolved = set.resolve_path(AnchoredPath::new(anchor, "./c.txt"));
+        assert_eq!(resolved, Some(sibling));
+    }
+
+    #[test]
+    fn resolve_path_returns_none_when_escaping_above_the_set_root() {
+        let mut set = FileSet::new();
+        let anchor = set.intern(&vpath("/a/b.txt"));
+        let resolved = set.resolve_path(AnchoredPath::new(anchor, "../../outside.txt"));
+        assert_eq!(resolved, None);
+    }
+
+    #[test]
+    fn resolve_path_returns_none_when_the_resolved_path_is_not_registered() {
+        let mut set = FileSet::new();
+        let anchor = set.intern(&vpath("/a/b.txt"));
+        let resolved = set.resolve_path(AnchoredPath::new(anchor, "unregistered.txt"));
+        assert_eq!(resolved, None);
+    }
+}
@@ -15,11 +15,11 @@
 //!  * **PhysicalFS** - the actual filesystem of the underlying OS
 //!  * **MemoryFS** - an ephemeral in-memory implementation (intended for unit tests)
 
-#[cfg(test)]
-#[macro_use]
-pub mod test_macros;
-
+pub mod altroot;
+pub mod embedded;
+pub mod fileset;
 pub mod memory;
+pub mod overlay;
 pub mod physical;
 
 use std::fmt::{Debug, Display};
@@ -70,10 +70,35 @@ pub trait SeekAndRead: Seek + Read {}
 
 impl<T> SeekAndRead for T where T: Seek + Read {}
 
+/// Adapts a `Box<dyn VfsFile>` to the narrower `SeekAndRead` trait object `open_file` returns.
+/// `VfsFile` and `SeekAndRead` are distinct traits (even though one is a superset of the other's
+/// bounds), so a plain trait-object coercion between them isn't available and this thin
+/// forwarding wrapper stands in for it.
+struct VfsFileAsSeekAndRead(Box<dyn VfsFile>);
+
+impl Read for VfsFileAsSeekAndRead {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Seek for VfsFileAsSeekAndRead {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+/// A file handle opened via [`VFS::open_with_options`], supporting reading, writing and seeking
+/// depending on the [`OpenOptions`] it was opened with
+pub trait VfsFile: Read + Write + Seek {}
+
+impl<T> VfsFile for T where T: Read + Write + Seek {}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum VFileType {
     File,
     Directory,
+    Symlink,
 }
 
 #[derive(Debug)]
@@ -82,27 +107,134 @@ pub struct VMetadata {
     pub len: u64,
 }
 
+/// Options for opening a file via [`VFS::open_with_options`], modeled after `std::fs::OpenOptions`
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OpenOptions {
+    pub read: bool,
+    pub write: bool,
+    pub create: bool,
+    pub create_new: bool,
+    pub append: bool,
+    pub truncate: bool,
+}
+
+impl OpenOptions {
+    /// Creates a blank new set of options, with every option initially set to `false`
+    pub fn new() -> OpenOptions {
+        Default::default()
+    }
+
+    /// Sets the option for read access
+    pub fn read(&mut self, read: bool) -> &mut OpenOptions {
+        self.read = read;
+        self
+    }
+
+    /// Sets the option for write access
+    pub fn write(&mut self, write: bool) -> &mut OpenOptions {
+        self.write = write;
+        self
+    }
+
+    /// Sets the option for creating the file if it does not exist
+    pub fn create(&mut self, create: bool) -> &mut OpenOptions {
+        self.create = create;
+        self
+    }
+
+    /// Sets the option to create a new file, failing if it already exists
+    pub fn create_new(&mut self, create_new: bool) -> &mut OpenOptions {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Sets the option for appending to the end of the file
+    pub fn append(&mut self, append: bool) -> &mut OpenOptions {
+        self.append = append;
+        self
+    }
+
+    /// Sets the option for truncating the file to 0 bytes once opened
+    pub fn truncate(&mut self, truncate: bool) -> &mut OpenOptions {
+        self.truncate = truncate;
+        self
+    }
+}
+
 pub trait VFS: Debug + Sync + Send {
     fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = String>>>;
     fn create_dir(&self, path: &str) -> Result<()>;
-    fn open_file(&self, path: &str) -> Result<Box<dyn SeekAndRead>>;
-    fn create_file(&self, path: &str) -> Result<Box<dyn Write>>;
-    fn append_file(&self, path: &str) -> Result<Box<dyn Write>>;
+
+    /// Opens a file at `path` with the given combination of read/write/create/append/truncate
+    /// flags. This is the primitive all other open methods are expressed in terms of.
+    fn open_with_options(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn VfsFile>>;
+
+    /// Opens a file for reading
+    fn open_file(&self, path: &str) -> Result<Box<dyn SeekAndRead>> {
+        let file = self.open_with_options(path, OpenOptions::new().read(true))?;
+        Ok(Box::new(VfsFileAsSeekAndRead(file)))
+    }
+
+    /// Opens a file for writing, truncating it if it exists already
+    fn create_file(&self, path: &str) -> Result<Box<dyn Write>> {
+        Ok(self.open_with_options(path, OpenOptions::new().write(true).create(true).truncate(true))?)
+    }
+
+    /// Opens a file for appending, creating it if it does not exist yet
+    fn append_file(&self, path: &str) -> Result<Box<dyn Write>> {
+        Ok(self.open_with_options(path, OpenOptions::new().write(true).create(true).append(true))?)
+    }
+
     fn metadata(&self, path: &str) -> Result<VMetadata>;
     fn exists(&self, path: &str) -> bool;
     fn remove_file(&self, path: &str) -> Result<()>;
     fn remove_dir(&self, path: &str) -> Result<()>;
+
+    /// Moves or renames a path to `dest`, overwriting it if it exists already
+    fn rename(&self, src: &str, dest: &str) -> Result<()>;
+
+    /// Copies the file at `src` to `dest`, overwriting it if it exists already. Implementations
+    /// may override this with a native copy; the default falls back to reading `src` and writing
+    /// it to `dest`.
+    fn copy_file(&self, src: &str, dest: &str) -> Result<()> {
+        let mut source = self.open_file(src)?;
+        let mut destination = self.create_file(dest)?;
+        std::io::copy(&mut source, &mut destination)?;
+        Ok(())
+    }
+
+    /// Creates a symlink at `link` pointing at `target`
+    fn create_symlink(&self, target: &str, link: &str) -> Result<()>;
+
+    /// Reads the target of the symlink at `path`
+    fn read_link(&self, path: &str) -> Result<String>;
+
+    /// Like [`VFS::metadata`], but does not follow a trailing symlink
+    fn symlink_metadata(&self, path: &str) -> Result<VMetadata>;
+
+    /// Maps the whole file at `path` into memory and returns a zero-copy `&[u8]` view over it.
+    /// This is faster than buffered `Read`/`Seek` for large files, at the cost that the mapping
+    /// may observe concurrent external modifications to the underlying file. Only available with
+    /// the `mmap` feature enabled. Implementations that have no native mapping fall back to this
+    /// default, which reports the operation as unsupported.
+    #[cfg(feature = "mmap")]
+    fn open_mmap(&self, path: &str) -> Result<Box<dyn AsRef<[u8]>>> {
+        let _ = path;
+        Err(VfsError::Other {
+            message: "mmap is not supported by this VFS".to_string(),
+        })
+    }
 }
 
 #[derive(Debug)]
 pub struct FileSystem {
-    vfs: Box<dyn VFS>,
+    pub(crate) vfs: Box<dyn VFS>,
 }
 
 #[derive(Debug)]
 pub struct VPath {
-    path: String,
-    fs: Arc<FileSystem>,
+    pub(crate) path: String,
+    pub(crate) fs: Arc<FileSystem>,
 }
 
 impl VPath {
@@ -160,6 +292,12 @@ impl VPath {
         Ok(())
     }
 
+    pub fn open_with_options(&self, options: &OpenOptions) -> Result<Box<dyn VfsFile>> {
+        self.fs
+            .vfs
+            .open_with_options(&self.path, options)
+            .with_context(|| format!("Could not open file '{}'", &self.path))
+    }
     pub fn open_file(&self) -> Result<Box<dyn SeekAndRead>> {
         self.fs
             .vfs
@@ -178,6 +316,49 @@ impl VPath {
             .append_file(&self.path)
             .with_context(|| format!("Could not open file '{}' for appending", &self.path))
     }
+    pub fn rename(&self, dest: &VPath) -> Result<()> {
+        self.fs
+            .vfs
+            .rename(&self.path, &dest.path)
+            .with_context(|| format!("Could not rename '{}' to '{}'", &self.path, &dest.path))
+    }
+
+    pub fn copy_file(&self, dest: &VPath) -> Result<()> {
+        self.fs
+            .vfs
+            .copy_file(&self.path, &dest.path)
+            .with_context(|| format!("Could not copy '{}' to '{}'", &self.path, &dest.path))
+    }
+
+    pub fn create_symlink(&self, target: &str) -> Result<()> {
+        self.fs
+            .vfs
+            .create_symlink(target, &self.path)
+            .with_context(|| format!("Could not create symlink '{}'", &self.path))
+    }
+
+    pub fn read_link(&self) -> Result<String> {
+        self.fs
+            .vfs
+            .read_link(&self.path)
+            .with_context(|| format!("Could not read link '{}'", &self.path))
+    }
+
+    pub fn symlink_metadata(&self) -> Result<VMetadata> {
+        self.fs
+            .vfs
+            .symlink_metadata(&self.path)
+            .with_context(|| format!("Could not get symlink metadata for '{}'", &self.path))
+    }
+
+    #[cfg(feature = "mmap")]
+    pub fn open_mmap(&self) -> Result<Box<dyn AsRef<[u8]>>> {
+        self.fs
+            .vfs
+            .open_mmap(&self.path)
+            .with_context(|| format!("Could not memory-map file '{}'", &self.path))
+    }
+
     pub fn remove_file(&self) -> Result<()> {
         self.fs
             .vfs
@@ -199,7 +380,7 @@ impl VPath {
         for child in self.read_dir()? {
             let metadata = child.metadata()?;
             match metadata.file_type {
-                VFileType::File => child.remove_file()?,
+                VFileType::File | VFileType::Symlink => child.remove_file()?,
                 VFileType::Directory => child.remove_dir_all()?,
             }
         }
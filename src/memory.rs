@@ -0,0 +1,565 @@
+//! An ephemeral in-memory file system, intended mainly for unit tests
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, RwLock};
+
+use crate::{OpenOptions, Result, VFileType, VMetadata, VfsError, VfsFile, VFS};
+
+#[derive(Debug, Clone)]
+enum MemoryNode {
+    File(Arc<RwLock<Vec<u8>>>),
+    Directory,
+    Symlink(String),
+}
+
+/// Maximum number of symlink hops followed before giving up, to break cycles
+const MAX_SYMLINK_DEPTH: usize = 16;
+
+/// Follows `path` through any chain of symlinks, returning the final (possibly still
+/// non-existent) path. A path that isn't a symlink is returned unchanged.
+fn resolve_symlinks(entries: &HashMap<String, MemoryNode>, path: &str) -> Result<String> {
+    let mut current = path.to_string();
+    for _ in 0..MAX_SYMLINK_DEPTH {
+        match entries.get(&current) {
+            Some(MemoryNode::Symlink(target)) => current = target.clone(),
+            _ => return Ok(current),
+        }
+    }
+    Err(VfsError::Other {
+        message: format!("Too many levels of symlinks resolving '{}'", path),
+    })
+}
+
+/// An ephemeral in-memory file system, intended mainly for unit tests
+#[derive(Debug, Default, Clone)]
+pub struct MemoryFS {
+    entries: Arc<RwLock<HashMap<String, MemoryNode>>>,
+}
+
+impl MemoryFS {
+    pub fn new() -> Self {
+        MemoryFS::default()
+    }
+}
+
+/// A handle to an open file in a [`MemoryFS`], reading and writing directly through to the
+/// shared buffer backing it, honoring the read/write flags it was opened with
+#[derive(Debug)]
+struct MemoryFile {
+    buffer: Arc<RwLock<Vec<u8>>>,
+    position: u64,
+    append: bool,
+    readable: bool,
+    writable: bool,
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if !self.readable {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "file was not opened for reading",
+            ));
+        }
+        let data = self.buffer.read().unwrap();
+        let start = self.position as usize;
+        if start >= data.len() {
+            return Ok(0);
+        }
+        let end = (start + buf.len()).min(data.len());
+        let n = end - start;
+        buf[..n].copy_from_slice(&data[start..end]);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if !self.writable {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "file was not opened for writing",
+            ));
+        }
+        let mut data = self.buffer.write().unwrap();
+        if self.append {
+            self.position = data.len() as u64;
+        }
+        let start = self.position as usize;
+        let end = start + buf.len();
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[start..end].copy_from_slice(buf);
+        self.position = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let len = self.buffer.read().unwrap().len() as u64;
+        let new_position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => len as i64 + offset,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if new_position < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek before start of file",
+            ));
+        }
+        self.position = new_position as u64;
+        Ok(self.position)
+    }
+}
+
+impl VFS for MemoryFS {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = String>>> {
+        let entries = self.entries.read().unwrap();
+        let prefix = format!("{}/", path);
+        let children: Vec<String> = entries
+            .keys()
+            .filter_map(|key| {
+                let rest = key.strip_prefix(&prefix)?;
+                if rest.is_empty() || rest.contains('/') {
+                    None
+                } else {
+                    Some(rest.to_string())
+                }
+            })
+            .collect();
+        Ok(Box::new(children.into_iter()))
+    }
+
+    fn create_dir(&self, path: &str) -> Result<()> {
+        let mut entries = self.entries.write().unwrap();
+        entries.insert(path.to_string(), MemoryNode::Directory);
+        Ok(())
+    }
+
+    fn open_with_options(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn VfsFile>> {
+        let mut entries = self.entries.write().unwrap();
+        let resolved = resolve_symlinks(&entries, path)?;
+        if options.create_new && entries.contains_key(&resolved) {
+            return Err(VfsError::Other {
+                message: format!("File '{}' already exists", path),
+            });
+        }
+        let buffer = match entries.get(&resolved) {
+            Some(MemoryNode::File(buffer)) => buffer.clone(),
+            Some(MemoryNode::Directory) => {
+                return Err(VfsError::Other {
+                    message: format!("'{}' is a directory", path),
+                })
+            }
+            Some(MemoryNode::Symlink(_)) => unreachable!("resolve_symlinks always follows symlinks fully"),
+            None if options.create || options.create_new => {
+                let buffer = Arc::new(RwLock::new(Vec::new()));
+                entries.insert(resolved.clone(), MemoryNode::File(buffer.clone()));
+                buffer
+            }
+            None => {
+                return Err(VfsError::FileNotFound {
+                    path: path.to_string(),
+                })
+            }
+        };
+        drop(entries);
+        if options.truncate {
+            buffer.write().unwrap().clear();
+        }
+        let position = if options.append {
+            buffer.read().unwrap().len() as u64
+        } else {
+            0
+        };
+        Ok(Box::new(MemoryFile {
+            buffer,
+            position,
+            append: options.append,
+            readable: options.read,
+            writable: options.write || options.append,
+        }))
+    }
+
+    fn metadata(&self, path: &str) -> Result<VMetadata> {
+        let entries = self.entries.read().unwrap();
+        let resolved = resolve_symlinks(&entries, path)?;
+        match entries.get(&resolved) {
+            Some(MemoryNode::File(buffer)) => Ok(VMetadata {
+                file_type: VFileType::File,
+                len: buffer.read().unwrap().len() as u64,
+            }),
+            Some(MemoryNode::Directory) => Ok(VMetadata {
+                file_type: VFileType::Directory,
+                len: 0,
+            }),
+            Some(MemoryNode::Symlink(_)) => unreachable!("resolve_symlinks always follows symlinks fully"),
+            None if resolved.is_empty() => Ok(VMetadata {
+                file_type: VFileType::Directory,
+                len: 0,
+            }),
+            None => Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        if path.is_empty() {
+            return true;
+        }
+        let entries = self.entries.read().unwrap();
+        match resolve_symlinks(&entries, path) {
+            Ok(resolved) => entries.contains_key(&resolved),
+            Err(_) => false,
+        }
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        let mut entries = self.entries.write().unwrap();
+        match entries.remove(path) {
+            Some(MemoryNode::File(_)) => Ok(()),
+            Some(other) => {
+                entries.insert(path.to_string(), other);
+                Err(VfsError::Other {
+                    message: format!("'{}' is not a file", path),
+                })
+            }
+            None => Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        let mut entries = self.entries.write().unwrap();
+        match entries.remove(path) {
+            Some(MemoryNode::Directory) => Ok(()),
+            Some(other) => {
+                entries.insert(path.to_string(), other);
+                Err(VfsError::Other {
+                    message: format!("'{}' is not a directory", path),
+                })
+            }
+            None => Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    fn rename(&self, src: &str, dest: &str) -> Result<()> {
+        if src == dest {
+            return Ok(());
+        }
+        let mut entries = self.entries.write().unwrap();
+        let node = entries.get(src).cloned().ok_or_else(|| VfsError::FileNotFound {
+            path: src.to_string(),
+        })?;
+        let dest_prefix = format!("{}/", dest);
+        if let MemoryNode::Directory = node {
+            let src_prefix = format!("{}/", src);
+            if dest == src || dest.starts_with(&src_prefix) {
+                return Err(VfsError::Other {
+                    message: format!("Cannot move directory '{}' into its own descendant '{}'", src, dest),
+                });
+            }
+            let moved: Vec<(String, MemoryNode)> = entries
+                .iter()
+                .filter(|(path, _)| path.as_str() == src || path.starts_with(&src_prefix))
+                .map(|(path, node)| (format!("{}{}", dest, &path[src.len()..]), node.clone()))
+                .collect();
+            // Fully replace whatever was at `dest` (file or directory subtree) rather than
+            // merging the moved subtree into it.
+            entries.retain(|path, _| {
+                path.as_str() != src && !path.starts_with(&src_prefix) && path.as_str() != dest && !path.starts_with(&dest_prefix)
+            });
+            for (path, node) in moved {
+                entries.insert(path, node);
+            }
+        } else {
+            entries.retain(|path, _| path.as_str() != dest && !path.starts_with(&dest_prefix));
+            entries.remove(src);
+            entries.insert(dest.to_string(), node);
+        }
+        Ok(())
+    }
+
+    fn copy_file(&self, src: &str, dest: &str) -> Result<()> {
+        let mut entries = self.entries.write().unwrap();
+        let resolved = resolve_symlinks(&entries, src)?;
+        if let Some(MemoryNode::Directory) = entries.get(dest) {
+            return Err(VfsError::Other {
+                message: format!("'{}' is a directory", dest),
+            });
+        }
+        match entries.get(&resolved) {
+            Some(MemoryNode::File(buffer)) => {
+                let contents = buffer.read().unwrap().clone();
+                entries.insert(dest.to_string(), MemoryNode::File(Arc::new(RwLock::new(contents))));
+                Ok(())
+            }
+            Some(MemoryNode::Directory) => Err(VfsError::Other {
+                message: format!("'{}' is a directory", src),
+            }),
+            Some(MemoryNode::Symlink(_)) => unreachable!("resolve_symlinks always follows symlinks fully"),
+            None => Err(VfsError::FileNotFound {
+                path: src.to_string(),
+            }),
+        }
+    }
+
+    fn create_symlink(&self, target: &str, link: &str) -> Result<()> {
+        let mut entries = self.entries.write().unwrap();
+        if entries.contains_key(link) {
+            return Err(VfsError::Other {
+                message: format!("'{}' already exists", link),
+            });
+        }
+        entries.insert(link.to_string(), MemoryNode::Symlink(target.to_string()));
+        Ok(())
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        let entries = self.entries.read().unwrap();
+        match entries.get(path) {
+            Some(MemoryNode::Symlink(target)) => Ok(target.clone()),
+            Some(_) => Err(VfsError::Other {
+                message: format!("'{}' is not a symlink", path),
+            }),
+            None => Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<VMetadata> {
+        let entries = self.entries.read().unwrap();
+        match entries.get(path) {
+            Some(MemoryNode::Symlink(_)) => Ok(VMetadata {
+                file_type: VFileType::Symlink,
+                len: 0,
+            }),
+            Some(MemoryNode::File(buffer)) => Ok(VMetadata {
+                file_type: VFileType::File,
+                len: buffer.read().unwrap().len() as u64,
+            }),
+            Some(MemoryNode::Directory) => Ok(VMetadata {
+                file_type: VFileType::Directory,
+                len: 0,
+            }),
+            None if path.is_empty() => Ok(VMetadata {
+                file_type: VFileType::Directory,
+                len: 0,
+            }),
+            None => Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    /// Returns a copy of the file's contents at the time of the call, not a zero-copy view of the
+    /// shared buffer; later writes to the file are not reflected in it.
+    #[cfg(feature = "mmap")]
+    fn open_mmap(&self, path: &str) -> Result<Box<dyn AsRef<[u8]>>> {
+        let entries = self.entries.read().unwrap();
+        let resolved = resolve_symlinks(&entries, path)?;
+        match entries.get(&resolved) {
+            Some(MemoryNode::File(buffer)) => Ok(Box::new(buffer.read().unwrap().clone())),
+            Some(MemoryNode::Directory) => Err(VfsError::Other {
+                message: format!("'{}' is a directory", path),
+            }),
+            Some(MemoryNode::Symlink(_)) => unreachable!("resolve_symlinks always follows symlinks fully"),
+            None => Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_file_missing_without_create_fails() {
+        let fs = MemoryFS::new();
+        assert!(fs.open_with_options("/missing.txt", OpenOptions::new().read(true)).is_err());
+    }
+
+    #[test]
+    fn create_writes_and_read_file_reads_back() {
+        let fs = MemoryFS::new();
+        fs.open_with_options("/a.txt", OpenOptions::new().write(true).create(true))
+            .unwrap()
+            .write_all(b"hello")
+            .unwrap();
+        let mut contents = String::new();
+        fs.open_with_options("/a.txt", OpenOptions::new().read(true))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "hello");
+    }
+
+    #[test]
+    fn create_new_fails_if_file_already_exists() {
+        let fs = MemoryFS::new();
+        fs.create_file("/a.txt").unwrap();
+        let result = fs.open_with_options("/a.txt", OpenOptions::new().write(true).create_new(true));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn truncate_clears_existing_contents() {
+        let fs = MemoryFS::new();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.open_with_options("/a.txt", OpenOptions::new().write(true).truncate(true))
+            .unwrap();
+        assert_eq!(fs.metadata("/a.txt").unwrap().len, 0);
+    }
+
+    #[test]
+    fn append_writes_after_existing_contents() {
+        let fs = MemoryFS::new();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.append_file("/a.txt").unwrap().write_all(b" world").unwrap();
+        let mut contents = String::new();
+        fs.open_file("/a.txt").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello world");
+    }
+
+    #[test]
+    fn rename_overwrites_an_existing_file_at_dest() {
+        let fs = MemoryFS::new();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.create_file("/b.txt").unwrap().write_all(b"world").unwrap();
+        fs.rename("/a.txt", "/b.txt").unwrap();
+        assert!(!fs.exists("/a.txt"));
+        let mut contents = String::new();
+        fs.open_file("/b.txt").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+    }
+
+    #[test]
+    fn rename_moves_a_directory_and_its_contents() {
+        let fs = MemoryFS::new();
+        fs.create_dir("/dir").unwrap();
+        fs.create_file("/dir/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.rename("/dir", "/moved").unwrap();
+        assert!(!fs.exists("/dir"));
+        assert!(fs.exists("/moved"));
+        assert!(fs.exists("/moved/a.txt"));
+    }
+
+    #[test]
+    fn rename_rejects_moving_a_directory_into_its_own_descendant() {
+        let fs = MemoryFS::new();
+        fs.create_dir("/dir").unwrap();
+        fs.create_dir("/dir/sub").unwrap();
+        assert!(fs.rename("/dir", "/dir/sub").is_err());
+    }
+
+    #[test]
+    fn rename_directory_fully_replaces_a_nonempty_destination_directory() {
+        let fs = MemoryFS::new();
+        fs.create_dir("/src").unwrap();
+        fs.create_file("/src/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.create_dir("/dst").unwrap();
+        fs.create_file("/dst/old.txt").unwrap();
+        fs.rename("/src", "/dst").unwrap();
+        assert!(!fs.exists("/src"));
+        assert!(fs.exists("/dst/a.txt"));
+        assert!(!fs.exists("/dst/old.txt"));
+    }
+
+    #[test]
+    fn copy_file_overwrites_dest_and_leaves_src_untouched() {
+        let fs = MemoryFS::new();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.create_file("/b.txt").unwrap().write_all(b"world").unwrap();
+        fs.copy_file("/a.txt", "/b.txt").unwrap();
+        let mut contents = String::new();
+        fs.open_file("/b.txt").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+        assert!(fs.exists("/a.txt"));
+    }
+
+    #[test]
+    fn copy_file_rejects_a_dest_that_is_a_directory() {
+        let fs = MemoryFS::new();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.create_dir("/dir").unwrap();
+        fs.create_file("/dir/child.txt").unwrap();
+        assert!(fs.copy_file("/a.txt", "/dir").is_err());
+        assert!(fs.exists("/dir/child.txt"));
+    }
+
+    #[test]
+    fn read_only_handle_cannot_write() {
+        let fs = MemoryFS::new();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        let mut file = fs.open_with_options("/a.txt", OpenOptions::new().read(true)).unwrap();
+        assert!(file.write_all(b"XXXXX").is_err());
+        let mut contents = String::new();
+        fs.open_file("/a.txt").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+    }
+
+    #[test]
+    fn write_only_handle_cannot_read() {
+        let fs = MemoryFS::new();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        let mut file = fs
+            .open_with_options("/a.txt", OpenOptions::new().write(true))
+            .unwrap();
+        let mut buf = [0u8; 1];
+        assert!(file.read(&mut buf).is_err());
+    }
+
+    #[test]
+    fn create_symlink_and_read_link_round_trip() {
+        let fs = MemoryFS::new();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.create_symlink("/a.txt", "/link").unwrap();
+        assert_eq!(fs.read_link("/link").unwrap(), "/a.txt");
+        let mut contents = String::new();
+        fs.open_file("/link").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+        assert_eq!(fs.symlink_metadata("/link").unwrap().file_type, VFileType::Symlink);
+        assert_eq!(fs.metadata("/link").unwrap().file_type, VFileType::File);
+    }
+
+    #[test]
+    fn opening_a_dangling_symlink_fails() {
+        let fs = MemoryFS::new();
+        fs.create_symlink("/missing.txt", "/link").unwrap();
+        assert!(fs.open_file("/link").is_err());
+    }
+
+    #[test]
+    fn resolving_a_symlink_cycle_fails_instead_of_looping_forever() {
+        let fs = MemoryFS::new();
+        fs.create_symlink("/b", "/a").unwrap();
+        fs.create_symlink("/a", "/b").unwrap();
+        assert!(fs.open_file("/a").is_err());
+        assert!(!fs.exists("/a"));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn open_mmap_reflects_the_files_contents() {
+        let fs = MemoryFS::new();
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        let mapping = fs.open_mmap("/a.txt").unwrap();
+        assert_eq!((*mapping).as_ref(), b"hello");
+    }
+}
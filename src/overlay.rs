@@ -0,0 +1,344 @@
+//! An overlay of multiple file systems, copy-on-write on top of a single writable layer
+//!
+//! Reads consult the layers top-to-bottom and return the first hit; writes always land on the
+//! single writable top layer, copying a file up from whichever lower layer has it first if it is
+//! missing from the top layer and being opened with write intent. Deleting a path that only
+//! exists in a read-only lower layer can't actually
+//! remove it there, so the deletion is instead recorded as a whiteout marker in the top layer,
+//! which hides the path from the merged view. This lets callers stack a read-only base (an
+//! `EmbeddedFS` or `PhysicalFS`) under a scratch `MemoryFS` for tests and sandboxes.
+
+use std::collections::HashSet;
+use std::io;
+
+use crate::{OpenOptions, Result, VFileType, VMetadata, VfsError, VfsFile, VPath, VFS};
+
+const WHITEOUT_PREFIX: &str = "/.wh.";
+
+/// A stack of file systems layered on top of each other, ordered top-to-bottom
+#[derive(Debug)]
+pub struct OverlayFS {
+    layers: Vec<VPath>,
+}
+
+impl OverlayFS {
+    /// Creates a new overlay. `layers` is ordered top-to-bottom: `layers[0]` is the single
+    /// writable layer, the rest are consulted read-only, in order, for reads.
+    pub fn new(layers: Vec<VPath>) -> Self {
+        assert!(!layers.is_empty(), "OverlayFS needs at least one layer");
+        OverlayFS { layers }
+    }
+
+    fn top(&self) -> &VPath {
+        &self.layers[0]
+    }
+
+    fn layer_path(&self, layer: &VPath, path: &str) -> String {
+        format!("{}{}", layer.path(), path)
+    }
+
+    /// Encodes `path` as a single flat file name directly under the top layer's root, so that
+    /// recording a whiteout never requires creating intermediate directories there, regardless of
+    /// what `VFS` backs the top layer. `_` is doubled and `/` is replaced with `_s` so the
+    /// encoding is collision-free and reversible.
+    fn whiteout_path(&self, path: &str) -> String {
+        let escaped = path.trim_start_matches('/').replace('_', "__").replace('/', "_s");
+        format!("{}{}", WHITEOUT_PREFIX, escaped)
+    }
+
+    fn is_whited_out(&self, path: &str) -> bool {
+        self.top().fs.vfs.exists(&self.layer_path(self.top(), &self.whiteout_path(path)))
+    }
+
+    fn add_whiteout(&self, path: &str) -> Result<()> {
+        self.top()
+            .fs
+            .vfs
+            .create_file(&self.layer_path(self.top(), &self.whiteout_path(path)))?;
+        Ok(())
+    }
+
+    fn clear_whiteout(&self, path: &str) -> Result<()> {
+        let whiteout = self.layer_path(self.top(), &self.whiteout_path(path));
+        if self.top().fs.vfs.exists(&whiteout) {
+            self.top().fs.vfs.remove_file(&whiteout)?;
+        }
+        Ok(())
+    }
+
+    /// Whether `name` (a bare directory entry name, not a path) is itself a whiteout marker, and
+    /// so should never show up as a regular entry in a merged [`VFS::read_dir`] listing
+    fn is_whiteout_marker_name(name: &str) -> bool {
+        name.starts_with(WHITEOUT_PREFIX.trim_start_matches('/'))
+    }
+}
+
+impl VFS for OverlayFS {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = String>>> {
+        let mut seen = HashSet::new();
+        let mut children = Vec::new();
+        for layer in &self.layers {
+            let full = self.layer_path(layer, path);
+            if let Ok(entries) = layer.fs.vfs.read_dir(&full) {
+                for name in entries {
+                    if Self::is_whiteout_marker_name(&name) || !seen.insert(name.clone()) {
+                        continue;
+                    }
+                    let child_path = format!("{}/{}", path, name);
+                    if !self.is_whited_out(&child_path) {
+                        children.push(name);
+                    }
+                }
+            }
+        }
+        Ok(Box::new(children.into_iter()))
+    }
+
+    fn create_dir(&self, path: &str) -> Result<()> {
+        self.clear_whiteout(path)?;
+        self.top().fs.vfs.create_dir(&self.layer_path(self.top(), path))
+    }
+
+    fn open_with_options(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn VfsFile>> {
+        let wants_write =
+            options.write || options.append || options.create || options.create_new || options.truncate;
+        if !wants_write {
+            if self.is_whited_out(path) {
+                return Err(VfsError::FileNotFound {
+                    path: path.to_string(),
+                });
+            }
+            for layer in &self.layers {
+                let full = self.layer_path(layer, path);
+                if layer.fs.vfs.exists(&full) {
+                    return layer.fs.vfs.open_with_options(&full, options);
+                }
+            }
+            return Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            });
+        }
+
+        let top_path = self.layer_path(self.top(), path);
+        if wants_write && !self.top().fs.vfs.exists(&top_path) && !self.is_whited_out(path) {
+            for layer in &self.layers[1..] {
+                let full = self.layer_path(layer, path);
+                if layer.fs.vfs.exists(&full) {
+                    let mut source = layer.fs.vfs.open_file(&full)?;
+                    let mut dest = self.top().fs.vfs.create_file(&top_path)?;
+                    io::copy(&mut source, &mut dest)?;
+                    break;
+                }
+            }
+        }
+        self.clear_whiteout(path)?;
+        self.top().fs.vfs.open_with_options(&top_path, options)
+    }
+
+    fn metadata(&self, path: &str) -> Result<VMetadata> {
+        if self.is_whited_out(path) {
+            return Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            });
+        }
+        for layer in &self.layers {
+            let full = self.layer_path(layer, path);
+            if layer.fs.vfs.exists(&full) {
+                return layer.fs.vfs.metadata(&full);
+            }
+        }
+        Err(VfsError::FileNotFound {
+            path: path.to_string(),
+        })
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        if self.is_whited_out(path) {
+            return false;
+        }
+        self.layers.iter().any(|layer| layer.fs.vfs.exists(&self.layer_path(layer, path)))
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        let top_path = self.layer_path(self.top(), path);
+        let existed_in_top = self.top().fs.vfs.exists(&top_path);
+        if existed_in_top {
+            self.top().fs.vfs.remove_file(&top_path)?;
+        }
+        let exists_below = self.layers[1..]
+            .iter()
+            .any(|layer| layer.fs.vfs.exists(&self.layer_path(layer, path)));
+        if exists_below {
+            self.add_whiteout(path)?;
+        } else if !existed_in_top {
+            return Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        let top_path = self.layer_path(self.top(), path);
+        let existed_in_top = self.top().fs.vfs.exists(&top_path);
+        if existed_in_top {
+            self.top().fs.vfs.remove_dir(&top_path)?;
+        }
+        let exists_below = self.layers[1..]
+            .iter()
+            .any(|layer| layer.fs.vfs.exists(&self.layer_path(layer, path)));
+        if exists_below {
+            self.add_whiteout(path)?;
+        } else if !existed_in_top {
+            return Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn rename(&self, src: &str, dest: &str) -> Result<()> {
+        if let VFileType::Directory = self.metadata(src)?.file_type {
+            return Err(VfsError::Other {
+                message: "OverlayFS does not support renaming directories".to_string(),
+            });
+        }
+        self.copy_file(src, dest)?;
+        self.remove_file(src)
+    }
+
+    fn create_symlink(&self, target: &str, link: &str) -> Result<()> {
+        self.clear_whiteout(link)?;
+        self.top()
+            .fs
+            .vfs
+            .create_symlink(target, &self.layer_path(self.top(), link))
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        if self.is_whited_out(path) {
+            return Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            });
+        }
+        for layer in &self.layers {
+            let full = self.layer_path(layer, path);
+            if layer.fs.vfs.exists(&full) {
+                return layer.fs.vfs.read_link(&full);
+            }
+        }
+        Err(VfsError::FileNotFound {
+            path: path.to_string(),
+        })
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<VMetadata> {
+        if self.is_whited_out(path) {
+            return Err(VfsError::FileNotFound {
+                path: path.to_string(),
+            });
+        }
+        for layer in &self.layers {
+            let full = self.layer_path(layer, path);
+            if layer.fs.vfs.exists(&full) {
+                return layer.fs.vfs.symlink_metadata(&full);
+            }
+        }
+        Err(VfsError::FileNotFound {
+            path: path.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryFS;
+
+    fn layer() -> VPath {
+        VPath::create(MemoryFS::new()).unwrap()
+    }
+
+    /// Creates a second handle onto the same underlying `VPath`, so a test can inspect a layer
+    /// after handing it off into an `OverlayFS`
+    fn dup(path: &VPath) -> VPath {
+        VPath {
+            path: path.path().to_string(),
+            fs: path.fs.clone(),
+        }
+    }
+
+    #[test]
+    fn plain_write_open_copies_a_file_up_from_a_lower_layer() {
+        let lower = layer();
+        lower.join("a.txt").create_file().unwrap().write_all(b"hello").unwrap();
+        let top = layer();
+        let overlay = OverlayFS::new(vec![dup(&top), dup(&lower)]);
+
+        overlay
+            .open_with_options("/a.txt", OpenOptions::new().write(true))
+            .unwrap()
+            .write_all(b"!")
+            .unwrap();
+
+        assert!(top.join("a.txt").exists());
+        let mut contents = String::new();
+        overlay.open_file("/a.txt").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "!ello");
+        assert_eq!(lower.join("a.txt").metadata().unwrap().len, 5);
+    }
+
+    #[test]
+    fn removing_a_lower_layer_file_hides_it_behind_a_whiteout() {
+        let lower = layer();
+        lower.join("a.txt").create_file().unwrap().write_all(b"hello").unwrap();
+        let top = layer();
+        let overlay = OverlayFS::new(vec![top, lower]);
+
+        overlay.remove_file("/a.txt").unwrap();
+
+        assert!(!overlay.exists("/a.txt"));
+        assert!(overlay.open_file("/a.txt").is_err());
+    }
+
+    #[test]
+    fn whiteout_for_a_nested_path_needs_no_intermediate_directories_in_the_top_layer() {
+        let lower = layer();
+        lower.join("dir").create_dir().unwrap();
+        lower.join("dir/a.txt").create_file().unwrap().write_all(b"hello").unwrap();
+        let top = layer();
+        let overlay = OverlayFS::new(vec![top, lower]);
+
+        overlay.remove_file("/dir/a.txt").unwrap();
+
+        assert!(!overlay.exists("/dir/a.txt"));
+    }
+
+    #[test]
+    fn read_dir_unions_and_dedups_entries_across_layers() {
+        let lower = layer();
+        lower.join("a.txt").create_file().unwrap();
+        lower.join("shared.txt").create_file().unwrap();
+        let top = layer();
+        top.join("b.txt").create_file().unwrap();
+        top.join("shared.txt").create_file().unwrap();
+        let overlay = OverlayFS::new(vec![top, lower]);
+
+        let mut names: Vec<String> = overlay.read_dir("").unwrap().collect();
+        names.sort();
+        assert_eq!(names, vec!["a.txt", "b.txt", "shared.txt"]);
+    }
+
+    #[test]
+    fn read_dir_omits_whited_out_entries() {
+        let lower = layer();
+        lower.join("a.txt").create_file().unwrap();
+        let top = layer();
+        let overlay = OverlayFS::new(vec![top, lower]);
+
+        overlay.remove_file("/a.txt").unwrap();
+
+        let names: Vec<String> = overlay.read_dir("").unwrap().collect();
+        assert!(names.is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+//! A "physical" file system implementation using the underlying OS file system
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{OpenOptions, Result, VFileType, VMetadata, VfsFile, VFS};
+
+/// A file system implementation using the underlying OS file system
+#[derive(Debug)]
+pub struct PhysicalFS {
+    root: PathBuf,
+}
+
+impl PhysicalFS {
+    pub fn new<T: Into<PathBuf>>(root: T) -> Self {
+        PhysicalFS { root: root.into() }
+    }
+
+    fn full_path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+}
+
+impl VFS for PhysicalFS {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = String>>> {
+        let entries = fs::read_dir(self.full_path(path))?;
+        Ok(Box::new(entries.filter_map(|entry| {
+            entry.ok()?.file_name().into_string().ok()
+        })))
+    }
+
+    fn create_dir(&self, path: &str) -> Result<()> {
+        fs::create_dir(self.full_path(path))?;
+        Ok(())
+    }
+
+    fn open_with_options(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn VfsFile>> {
+        let file = fs::OpenOptions::new()
+            .read(options.read)
+            .write(options.write)
+            .create(options.create)
+            .create_new(options.create_new)
+            .append(options.append)
+            .truncate(options.truncate)
+            .open(self.full_path(path))?;
+        Ok(Box::new(file))
+    }
+
+    fn metadata(&self, path: &str) -> Result<VMetadata> {
+        let metadata = fs::metadata(self.full_path(path))?;
+        Ok(VMetadata {
+            file_type: if metadata.is_dir() {
+                VFileType::Directory
+            } else {
+                VFileType::File
+            },
+            len: metadata.len(),
+        })
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.full_path(path).exists()
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        fs::remove_file(self.full_path(path))?;
+        Ok(())
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        fs::remove_dir(self.full_path(path))?;
+        Ok(())
+    }
+
+    fn rename(&self, src: &str, dest: &str) -> Result<()> {
+        fs::rename(self.full_path(src), self.full_path(dest))?;
+        Ok(())
+    }
+
+    fn copy_file(&self, src: &str, dest: &str) -> Result<()> {
+        fs::copy(self.full_path(src), self.full_path(dest))?;
+        Ok(())
+    }
+
+    fn create_symlink(&self, target: &str, link: &str) -> Result<()> {
+        let link_path = self.full_path(link);
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(target, &link_path)?;
+        #[cfg(windows)]
+        {
+            if self.full_path(target).is_dir() {
+                std::os::windows::fs::symlink_dir(target, &link_path)?;
+            } else {
+                std::os::windows::fs::symlink_file(target, &link_path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        let target = fs::read_link(self.full_path(path))?;
+        Ok(target.to_string_lossy().replace('\\', "/"))
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<VMetadata> {
+        let metadata = fs::symlink_metadata(self.full_path(path))?;
+        let file_type = if metadata.file_type().is_symlink() {
+            VFileType::Symlink
+        } else if metadata.is_dir() {
+            VFileType::Directory
+        } else {
+            VFileType::File
+        };
+        Ok(VMetadata {
+            file_type,
+            len: metadata.len(),
+        })
+    }
+
+    #[cfg(feature = "mmap")]
+    fn open_mmap(&self, path: &str) -> Result<Box<dyn AsRef<[u8]>>> {
+        let file = std::fs::File::open(self.full_path(path))?;
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Box::new(mmap))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    /// Creates a fresh, empty temporary directory for a test, named after the calling test to
+    /// avoid collisions between tests running in parallel
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rust-vfs-physical-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn create_symlink_stores_the_target_verbatim() {
+        let dir = temp_dir("symlink-verbatim");
+        let fs = PhysicalFS::new(&dir);
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.create_symlink("a.txt", "/link").unwrap();
+        assert_eq!(fs.read_link("/link").unwrap(), "a.txt");
+        let mut contents = String::new();
+        fs.open_file("/link").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+    }
+
+    #[test]
+    fn symlink_survives_the_root_being_moved() {
+        let dir = temp_dir("symlink-survives-move");
+        let fs = PhysicalFS::new(&dir);
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        fs.create_symlink("a.txt", "/link").unwrap();
+
+        let moved = temp_dir("symlink-survives-move-moved");
+        fs::remove_dir(&moved).unwrap();
+        fs::rename(&dir, &moved).unwrap();
+
+        let moved_fs = PhysicalFS::new(&moved);
+        let mut contents = String::new();
+        moved_fs.open_file("/link").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn open_mmap_reflects_the_files_contents() {
+        let dir = temp_dir("mmap-reflects-contents");
+        let fs = PhysicalFS::new(&dir);
+        fs.create_file("/a.txt").unwrap().write_all(b"hello").unwrap();
+        let mapping = fs.open_mmap("/a.txt").unwrap();
+        assert_eq!((*mapping).as_ref(), b"hello");
+    }
+}